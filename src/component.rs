@@ -1,11 +1,24 @@
 //! A glyph embedded in another glyph.
 
-use druid::kurbo::Affine;
+use std::collections::HashSet;
+
+use druid::kurbo::{Affine, BezPath};
 use druid::Data;
 use norad::GlyphName;
 
 use crate::path::EntityId;
 
+/// The outlines and nested component references of a glyph that can be
+/// resolved as the base of a `Component`.
+///
+/// A lookup function passed to `Component::decompose` returns this for a
+/// given `GlyphName`, letting decomposition walk into components that
+/// themselves contain components.
+pub struct DecomposedGlyph<'a> {
+    pub contours: &'a [BezPath],
+    pub components: &'a [Component],
+}
+
 #[derive(Debug, Data, Clone)]
 pub struct Component {
     pub base: GlyphName,
@@ -30,6 +43,54 @@ impl Component {
         }
     }
 
+    /// Resolve this component into a flat list of outline paths, recursing
+    /// into any sub-components of the base glyph.
+    ///
+    /// `lookup` fetches the contours and sub-components for a given base
+    /// glyph name. Each returned contour has had the full chain of
+    /// transforms (this component's, and that of every component it is
+    /// nested inside) applied to it, so the result can be spliced directly
+    /// into the edited glyph in place of the component reference.
+    ///
+    /// A glyph that references itself, directly or through a chain of
+    /// components, is not decomposed further; the cycle is silently
+    /// dropped rather than recursing forever.
+    pub fn decompose(
+        &self,
+        lookup: &impl Fn(&GlyphName) -> Option<DecomposedGlyph>,
+    ) -> Vec<BezPath> {
+        let mut seen = HashSet::new();
+        let mut out = Vec::new();
+        self.decompose_inner(self.transform, lookup, &mut seen, &mut out);
+        out
+    }
+
+    fn decompose_inner(
+        &self,
+        accumulated: Affine,
+        lookup: &impl Fn(&GlyphName) -> Option<DecomposedGlyph>,
+        seen: &mut HashSet<GlyphName>,
+        out: &mut Vec<BezPath>,
+    ) {
+        if !seen.insert(self.base.clone()) {
+            return;
+        }
+
+        if let Some(glyph) = lookup(&self.base) {
+            for contour in glyph.contours {
+                let mut path = contour.clone();
+                path.apply_affine(accumulated);
+                out.push(path);
+            }
+
+            for sub in glyph.components {
+                sub.decompose_inner(accumulated * sub.transform, lookup, seen, out);
+            }
+        }
+
+        seen.remove(&self.base);
+    }
+
     pub fn to_norad(&self) -> norad::glyph::Component {
         let base = self.base.clone();
         let transform = self.transform.into();
@@ -41,3 +102,128 @@ impl Component {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use druid::kurbo::Point;
+
+    use super::*;
+
+    /// A tiny in-memory "font" for feeding `Component::decompose` a lookup
+    /// function, mapping a glyph name to its contours and sub-components.
+    struct FakeFont(HashMap<GlyphName, (Vec<BezPath>, Vec<Component>)>);
+
+    impl FakeFont {
+        fn new() -> Self {
+            FakeFont(HashMap::new())
+        }
+
+        fn insert(&mut self, name: &str, contours: Vec<BezPath>, components: Vec<Component>) {
+            self.0.insert(GlyphName::from(name), (contours, components));
+        }
+
+        fn lookup(&self, name: &GlyphName) -> Option<DecomposedGlyph> {
+            self.0
+                .get(name)
+                .map(|(contours, components)| DecomposedGlyph {
+                    contours,
+                    components,
+                })
+        }
+    }
+
+    fn component(base: &str, transform: Affine) -> Component {
+        Component {
+            base: GlyphName::from(base),
+            transform,
+            id: EntityId::new_with_parent(0),
+        }
+    }
+
+    /// A single-element path whose one point makes it easy to read back
+    /// whatever transform was applied to it.
+    fn marker_path() -> BezPath {
+        let mut path = BezPath::new();
+        path.move_to((1.0, 0.0));
+        path
+    }
+
+    fn marker_point(path: &BezPath) -> Point {
+        match path.elements()[0] {
+            druid::kurbo::PathEl::MoveTo(p) => p,
+            _ => panic!("expected a MoveTo element"),
+        }
+    }
+
+    #[test]
+    fn decompose_simple_component() {
+        let mut font = FakeFont::new();
+        font.insert("dot", vec![marker_path()], vec![]);
+
+        let c = component("dot", Affine::translate((10.0, 0.0)));
+        let result = c.decompose(&|name| font.lookup(name));
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(marker_point(&result[0]), Point::new(11.0, 0.0));
+    }
+
+    #[test]
+    fn decompose_nested_chain_composes_transforms() {
+        let mut font = FakeFont::new();
+        font.insert("dotbase", vec![marker_path()], vec![]);
+        font.insert(
+            "accent",
+            vec![],
+            vec![component("dotbase", Affine::scale(2.0))],
+        );
+
+        let c = component("accent", Affine::translate((10.0, 0.0)));
+        let result = c.decompose(&|name| font.lookup(name));
+
+        assert_eq!(result.len(), 1);
+        // accumulated = translate(10, 0) * scale(2.0), applied to (1, 0)
+        assert_eq!(marker_point(&result[0]), Point::new(12.0, 0.0));
+    }
+
+    #[test]
+    fn decompose_direct_self_reference_terminates() {
+        let mut font = FakeFont::new();
+        font.insert(
+            "loop",
+            vec![marker_path()],
+            vec![component("loop", Affine::IDENTITY)],
+        );
+
+        let c = component("loop", Affine::IDENTITY);
+        let result = c.decompose(&|name| font.lookup(name));
+
+        // Only the top-level glyph's own contour is collected; the
+        // self-referencing sub-component is skipped rather than recursing
+        // forever.
+        assert_eq!(result.len(), 1);
+    }
+
+    #[test]
+    fn decompose_indirect_cycle_terminates() {
+        let mut font = FakeFont::new();
+        font.insert(
+            "a",
+            vec![marker_path()],
+            vec![component("b", Affine::IDENTITY)],
+        );
+        font.insert(
+            "b",
+            vec![marker_path()],
+            vec![component("a", Affine::IDENTITY)],
+        );
+
+        let c = component("a", Affine::IDENTITY);
+        let result = c.decompose(&|name| font.lookup(name));
+
+        // "a" -> "b" -> "a" each contribute their contour once; the second
+        // visit to "a" is cut off by the recursion-stack check.
+        assert_eq!(result.len(), 2);
+    }
+}
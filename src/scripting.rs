@@ -0,0 +1,420 @@
+//! A sandboxed scripting runtime for running user-supplied plugins against
+//! the open font.
+//!
+//! Scripts are compiled `.wasm` modules loaded through `wasmtime`. Each
+//! script is instantiated with a small host API that lets it enumerate the
+//! glyphs in the font, read and insert `Component` references, and append
+//! new outlines as `BezPath`s. The host marshals data across the ABI
+//! boundary and the resulting edits are applied through the same
+//! `from_norad`/`to_norad` conversion paths used when loading and saving a
+//! UFO, so a script can't produce glyph data the rest of the app wouldn't
+//! otherwise accept.
+//!
+//! Scripts run under a fuel budget (see `FUEL_BUDGET`), so a runaway loop
+//! traps instead of hanging the host thread.
+
+use std::path::Path;
+
+use druid::kurbo::{Affine, BezPath, PathEl, Point};
+use norad::GlyphName;
+use wasmtime::{Caller, Config, Engine, Linker, Memory, Module, Store, Trap, TrapCode};
+
+use crate::component::Component;
+use crate::path::EntityId;
+
+/// Units of work a script may perform before it's forcibly trapped. This is
+/// deliberately coarse -- it exists to bound a runaway script, not to meter
+/// real cost.
+const FUEL_BUDGET: u64 = 10_000_000;
+
+/// The on-the-wire encoding of a single `kurbo::PathEl`, written by the
+/// guest and read by `add_contour` as 7 little-endian f64s: a tag (0 move,
+/// 1 line, 2 quad, 3 curve, anything else close), followed by up to three
+/// (x, y) point pairs (unused trailing pairs are zeroed).
+const PATH_EL_STRIDE: usize = 7 * 8;
+
+/// Errors that can occur while loading or running a plugin script.
+#[derive(Debug)]
+pub enum ScriptError {
+    Wasm(wasmtime::Error),
+    /// An editing host function (`insert_component`, `add_contour`) was
+    /// called with a glyph index that doesn't exist. Carries the
+    /// out-of-range index that was passed. Read-only host functions return
+    /// 0 for an unknown glyph or component index instead.
+    UnknownGlyph(u32),
+    /// The script exceeded `FUEL_BUDGET` without finishing.
+    FuelExhausted,
+}
+
+impl From<wasmtime::Error> for ScriptError {
+    fn from(err: wasmtime::Error) -> Self {
+        ScriptError::Wasm(err)
+    }
+}
+
+/// A single glyph edit requested by a script, applied to the font after the
+/// script finishes running.
+pub enum GlyphEdit {
+    AddComponent { glyph: GlyphName, component: Component },
+    AddContour { glyph: GlyphName, contour: BezPath },
+}
+
+/// A glyph as exposed to a running script: its name and the components it
+/// already contains. Scripts can't see outline data (only add to it), since
+/// nothing in the host API needs to mutate existing contours.
+pub struct ScriptGlyph {
+    pub name: GlyphName,
+    pub components: Vec<Component>,
+}
+
+/// The state made available to a running script: the set of glyphs it can
+/// see, the edits it has requested so far, and the first hard error it's
+/// hit, if any.
+pub struct ScriptHost {
+    glyphs: Vec<ScriptGlyph>,
+    edits: Vec<GlyphEdit>,
+    error: Option<ScriptError>,
+}
+
+impl ScriptHost {
+    fn new(glyphs: Vec<ScriptGlyph>) -> Self {
+        ScriptHost {
+            glyphs,
+            edits: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn glyph_name(&self, index: u32) -> Option<&GlyphName> {
+        self.glyphs.get(index as usize).map(|g| &g.name)
+    }
+
+    fn component(&self, glyph_index: u32, component_index: u32) -> Option<&Component> {
+        self.glyphs
+            .get(glyph_index as usize)?
+            .components
+            .get(component_index as usize)
+    }
+
+    /// Record a host-side error, keeping the first one if called more than
+    /// once (a host function can't abort the wasm call in progress, so the
+    /// script may keep running after the first bad index).
+    fn fail(&mut self, err: ScriptError) {
+        if self.error.is_none() {
+            self.error = Some(err);
+        }
+    }
+}
+
+/// Write `bytes` into guest memory at `ptr`, returning the number of bytes
+/// written (0 if `ptr`/length would run off the end of memory).
+fn write_bytes(memory: &Memory, caller: &mut Caller<'_, ScriptHost>, ptr: u32, bytes: &[u8]) -> u32 {
+    match memory.write(&mut *caller, ptr as usize, bytes) {
+        Ok(()) => bytes.len() as u32,
+        Err(_) => 0,
+    }
+}
+
+fn guest_memory(caller: &mut Caller<'_, ScriptHost>) -> Option<Memory> {
+    caller.get_export("memory")?.into_memory()
+}
+
+/// A loaded plugin, ready to be run against a font's glyph set.
+pub struct Script {
+    engine: Engine,
+    module: Module,
+}
+
+impl Script {
+    /// Compile a `.wasm` module from disk.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScriptError> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::from_file(&engine, path.as_ref())?;
+        Ok(Script { engine, module })
+    }
+
+    /// Run the script against the given glyphs, returning the edits it
+    /// requested. The caller is responsible for applying the returned
+    /// `GlyphEdit`s to the actual font, via the same norad conversion paths
+    /// used elsewhere in the app.
+    pub fn run(&self, glyphs: Vec<ScriptGlyph>) -> Result<Vec<GlyphEdit>, ScriptError> {
+        let mut linker: Linker<ScriptHost> = Linker::new(&self.engine);
+
+        linker.func_wrap("env", "glyph_count", |host: Caller<'_, ScriptHost>| {
+            host.data().glyphs.len() as u32
+        })?;
+
+        linker.func_wrap(
+            "env",
+            "glyph_name_len",
+            |host: Caller<'_, ScriptHost>, glyph_index: u32| {
+                host.data()
+                    .glyph_name(glyph_index)
+                    .map(|name| name.len() as u32)
+                    .unwrap_or(0)
+            },
+        )?;
+
+        linker.func_wrap(
+            "env",
+            "glyph_name_read",
+            |mut host: Caller<'_, ScriptHost>, glyph_index: u32, ptr: u32| -> u32 {
+                let bytes = match host.data().glyph_name(glyph_index) {
+                    Some(name) => name.as_bytes().to_vec(),
+                    None => return 0,
+                };
+                match guest_memory(&mut host) {
+                    Some(memory) => write_bytes(&memory, &mut host, ptr, &bytes),
+                    None => 0,
+                }
+            },
+        )?;
+
+        linker.func_wrap(
+            "env",
+            "component_count",
+            |host: Caller<'_, ScriptHost>, glyph_index: u32| {
+                host.data()
+                    .glyphs
+                    .get(glyph_index as usize)
+                    .map(|g| g.components.len() as u32)
+                    .unwrap_or(0)
+            },
+        )?;
+
+        linker.func_wrap(
+            "env",
+            "component_base_len",
+            |host: Caller<'_, ScriptHost>, glyph_index: u32, component_index: u32| {
+                host.data()
+                    .component(glyph_index, component_index)
+                    .map(|c| c.base.len() as u32)
+                    .unwrap_or(0)
+            },
+        )?;
+
+        linker.func_wrap(
+            "env",
+            "component_base_read",
+            |mut host: Caller<'_, ScriptHost>,
+             glyph_index: u32,
+             component_index: u32,
+             ptr: u32|
+             -> u32 {
+                let bytes = match host.data().component(glyph_index, component_index) {
+                    Some(c) => c.base.as_bytes().to_vec(),
+                    None => return 0,
+                };
+                match guest_memory(&mut host) {
+                    Some(memory) => write_bytes(&memory, &mut host, ptr, &bytes),
+                    None => 0,
+                }
+            },
+        )?;
+
+        linker.func_wrap(
+            "env",
+            "component_transform_read",
+            |mut host: Caller<'_, ScriptHost>,
+             glyph_index: u32,
+             component_index: u32,
+             ptr: u32|
+             -> u32 {
+                let coeffs = match host.data().component(glyph_index, component_index) {
+                    Some(c) => c.transform.as_coeffs(),
+                    None => return 0,
+                };
+                let mut bytes = [0u8; 48];
+                for (i, v) in coeffs.iter().enumerate() {
+                    bytes[i * 8..i * 8 + 8].copy_from_slice(&v.to_le_bytes());
+                }
+                match guest_memory(&mut host) {
+                    Some(memory) => write_bytes(&memory, &mut host, ptr, &bytes),
+                    None => 0,
+                }
+            },
+        )?;
+
+        linker.func_wrap(
+            "env",
+            "insert_component",
+            |mut host: Caller<'_, ScriptHost>,
+             glyph_index: u32,
+             base_index: u32,
+             a: f64,
+             b: f64,
+             c: f64,
+             d: f64,
+             e: f64,
+             f: f64| {
+                let data = host.data_mut();
+                let glyph = match data.glyph_name(glyph_index).cloned() {
+                    Some(glyph) => glyph,
+                    None => return data.fail(ScriptError::UnknownGlyph(glyph_index)),
+                };
+                let base = match data.glyph_name(base_index).cloned() {
+                    Some(base) => base,
+                    None => return data.fail(ScriptError::UnknownGlyph(base_index)),
+                };
+                let component = Component {
+                    base,
+                    transform: Affine::new([a, b, c, d, e, f]),
+                    id: EntityId::next(),
+                };
+                data.edits.push(GlyphEdit::AddComponent { glyph, component });
+            },
+        )?;
+
+        linker.func_wrap(
+            "env",
+            "add_contour",
+            |mut host: Caller<'_, ScriptHost>, glyph_index: u32, ptr: u32, element_count: u32| {
+                let memory = match guest_memory(&mut host) {
+                    Some(memory) => memory,
+                    None => return,
+                };
+                let glyph = match host.data().glyph_name(glyph_index).cloned() {
+                    Some(glyph) => glyph,
+                    None => return host.data_mut().fail(ScriptError::UnknownGlyph(glyph_index)),
+                };
+                // `element_count` comes straight from the guest; size the
+                // host buffer against the guest's own linear memory rather
+                // than trusting it outright, or a script could ask for an
+                // arbitrarily huge allocation with a single u32.
+                let byte_len = element_count as u64 * PATH_EL_STRIDE as u64;
+                if ptr as u64 + byte_len > memory.data_size(&host) as u64 {
+                    return;
+                }
+                let mut buf = vec![0u8; byte_len as usize];
+                if memory.read(&host, ptr as usize, &mut buf).is_err() {
+                    return;
+                }
+                let contour = decode_path(&buf);
+                host.data_mut()
+                    .edits
+                    .push(GlyphEdit::AddContour { glyph, contour });
+            },
+        )?;
+
+        let mut store = Store::new(&self.engine, ScriptHost::new(glyphs));
+        store.set_fuel(FUEL_BUDGET)?;
+        let instance = linker.instantiate(&mut store, &self.module)?;
+        let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+        if let Err(err) = run.call(&mut store, ()) {
+            // Only a genuine fuel exhaustion trap is reported as
+            // `FuelExhausted`; any other trap (OOB access, div-by-zero, an
+            // explicit `unreachable`, ...) is a real script bug and should
+            // surface as such rather than being mistaken for the budget.
+            return match err.downcast_ref::<Trap>().and_then(Trap::trap_code) {
+                Some(TrapCode::OutOfFuel) => Err(ScriptError::FuelExhausted),
+                _ => Err(ScriptError::Wasm(err)),
+            };
+        }
+
+        let host = store.into_data();
+        match host.error {
+            Some(err) => Err(err),
+            None => Ok(host.edits),
+        }
+    }
+}
+
+/// Decode a buffer of `PATH_EL_STRIDE`-byte records (see `PATH_EL_STRIDE`
+/// for the layout) into a `BezPath`.
+fn decode_path(buf: &[u8]) -> BezPath {
+    let mut path = BezPath::new();
+    for record in buf.chunks_exact(PATH_EL_STRIDE) {
+        let mut floats = [0f64; 7];
+        for (i, chunk) in record.chunks_exact(8).enumerate() {
+            floats[i] = f64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        let pt = |i: usize| Point::new(floats[i], floats[i + 1]);
+        let el = match floats[0] as u32 {
+            0 => PathEl::MoveTo(pt(1)),
+            1 => PathEl::LineTo(pt(1)),
+            2 => PathEl::QuadTo(pt(1), pt(3)),
+            3 => PathEl::CurveTo(pt(1), pt(3), pt(5)),
+            _ => PathEl::ClosePath,
+        };
+        path.push(el);
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(tag: f64, coords: [f64; 6]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PATH_EL_STRIDE);
+        bytes.extend_from_slice(&tag.to_le_bytes());
+        for v in coords {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn decode_path_moveto_lineto_close() {
+        let mut buf = Vec::new();
+        buf.extend(record(0.0, [1.0, 2.0, 0.0, 0.0, 0.0, 0.0]));
+        buf.extend(record(1.0, [3.0, 4.0, 0.0, 0.0, 0.0, 0.0]));
+        buf.extend(record(4.0, [0.0; 6]));
+
+        let els: Vec<_> = decode_path(&buf).elements().to_vec();
+        assert_eq!(
+            els,
+            vec![
+                PathEl::MoveTo(Point::new(1.0, 2.0)),
+                PathEl::LineTo(Point::new(3.0, 4.0)),
+                PathEl::ClosePath,
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_path_curve_and_quad() {
+        let mut buf = Vec::new();
+        buf.extend(record(2.0, [1.0, 1.0, 2.0, 2.0, 0.0, 0.0]));
+        buf.extend(record(3.0, [1.0, 1.0, 2.0, 2.0, 3.0, 3.0]));
+
+        let els: Vec<_> = decode_path(&buf).elements().to_vec();
+        assert_eq!(
+            els,
+            vec![
+                PathEl::QuadTo(Point::new(1.0, 1.0), Point::new(2.0, 2.0)),
+                PathEl::CurveTo(
+                    Point::new(1.0, 1.0),
+                    Point::new(2.0, 2.0),
+                    Point::new(3.0, 3.0)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn script_host_glyph_and_component_bounds() {
+        let mut host = ScriptHost::new(vec![ScriptGlyph {
+            name: GlyphName::from("a"),
+            components: vec![Component {
+                base: GlyphName::from("dot"),
+                transform: Affine::IDENTITY,
+                id: EntityId::next(),
+            }],
+        }]);
+
+        assert_eq!(host.glyph_name(0), Some(&GlyphName::from("a")));
+        assert!(host.glyph_name(1).is_none());
+
+        assert!(host.component(0, 0).is_some());
+        assert!(host.component(0, 1).is_none());
+        assert!(host.component(1, 0).is_none());
+
+        assert!(host.error.is_none());
+        host.fail(ScriptError::UnknownGlyph(7));
+        host.fail(ScriptError::UnknownGlyph(9));
+        assert!(matches!(host.error, Some(ScriptError::UnknownGlyph(7))));
+    }
+}
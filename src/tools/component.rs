@@ -0,0 +1,246 @@
+//! A tool for embedding another glyph as a `Component` and then adjusting
+//! its placement on-canvas.
+//!
+//! The tool has two phases: first a click on the canvas submits
+//! `consts::cmd::SHOW_GLYPH_PICKER`, asking the app to prompt the user for
+//! a base glyph by name; once that picker replies with
+//! `consts::cmd::COMPONENT_BASE_CHOSEN`, a new `Component` is dropped into
+//! the current glyph at the point that was originally clicked. From there
+//! the tool hit-tests move/scale/rotate handles at that component's
+//! bounding box and lets the user drag them, writing the result straight
+//! back into `Component::transform`. The tool does not paint the handles
+//! itself.
+
+use druid::kurbo::{Affine, Point, Rect, Vec2};
+use druid::{Command, Env, EventCtx, MouseEvent};
+use norad::GlyphName;
+
+use crate::component::Component;
+use crate::consts;
+use crate::edit_session::EditSession;
+use crate::path::EntityId;
+use crate::tools::{EditType, Tool, ToolId};
+
+/// Offset, in canvas units along each axis, from the component's bounding
+/// box max corner to the rotate handle (+x and -y).
+const ROTATE_HANDLE_OFFSET: f64 = 24.0;
+/// Radius of a draggable handle's circular hit-test area.
+const HANDLE_HIT_RADIUS: f64 = 5.0;
+
+/// Which part of the on-canvas manipulator is being dragged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Handle {
+    Move,
+    Scale,
+    Rotate,
+}
+
+enum State {
+    /// No component has been placed yet; waiting for a click.
+    Idle,
+    /// The user clicked at `origin` and `SHOW_GLYPH_PICKER` is in flight;
+    /// waiting for the app to reply with `COMPONENT_BASE_CHOSEN`.
+    Picking { origin: Point },
+    /// A component exists and its handles are selectable.
+    Placed { id: EntityId },
+    /// The user is actively dragging a handle of the given component.
+    Dragging {
+        id: EntityId,
+        handle: Handle,
+        start_transform: Affine,
+        start_mouse: Point,
+        /// The component's origin, snapshotted once when the drag starts.
+        /// Scale and rotate pivot around this fixed point rather than the
+        /// global origin, and rather than re-querying the session (whose
+        /// transform is already being mutated by this same drag).
+        pivot: Point,
+    },
+}
+
+/// The "Component" tool: drops a reference to another glyph into the one
+/// being edited, and exposes move/scale/rotate handles for it.
+pub struct ComponentTool {
+    state: State,
+}
+
+impl Default for ComponentTool {
+    fn default() -> Self {
+        ComponentTool { state: State::Idle }
+    }
+}
+
+impl ComponentTool {
+    /// Insert a new `Component` referencing `base` into the session at
+    /// `origin`, and select it for immediate manipulation.
+    fn place_component(&mut self, session: &mut EditSession, base: GlyphName, origin: Point) {
+        let id = EntityId::next();
+        let component = Component {
+            base,
+            transform: Affine::translate(origin.to_vec2()),
+            id,
+        };
+        session.add_component(component);
+        self.state = State::Placed { id };
+    }
+
+    fn handle_for_point(&self, session: &EditSession, id: EntityId, pt: Point) -> Option<Handle> {
+        let component = session.component_for_id(id)?;
+        let bbox = session.component_bounds(component)?;
+        handle_at_bbox(bbox, pt)
+    }
+}
+
+/// Hit-test `pt` against the move/scale/rotate handles drawn around `bbox`.
+/// Pulled out of `handle_for_point` so the geometry can be unit-tested
+/// without needing a real `EditSession`.
+fn handle_at_bbox(bbox: Rect, pt: Point) -> Option<Handle> {
+    let corner = bbox.origin() + bbox.size().to_vec2();
+    let rotate_pt = corner + Vec2::new(ROTATE_HANDLE_OFFSET, -ROTATE_HANDLE_OFFSET);
+
+    if pt.distance(rotate_pt) <= HANDLE_HIT_RADIUS {
+        Some(Handle::Rotate)
+    } else if pt.distance(corner) <= HANDLE_HIT_RADIUS {
+        Some(Handle::Scale)
+    } else if bbox.contains(pt) {
+        Some(Handle::Move)
+    } else {
+        None
+    }
+}
+
+/// Wrap `transform` so it pivots around `pivot` instead of the global
+/// origin: `translate(pivot) * transform * translate(-pivot)`.
+fn pivot_transform(pivot: Point, transform: Affine) -> Affine {
+    Affine::translate(pivot.to_vec2()) * transform * Affine::translate(-pivot.to_vec2())
+}
+
+impl Tool for ComponentTool {
+    fn name(&self) -> ToolId {
+        "Component"
+    }
+
+    fn mouse_down(&mut self, event: &MouseEvent, ctx: &mut EventCtx, session: &mut EditSession, _env: &Env) {
+        let id = match self.state {
+            State::Idle => {
+                self.state = State::Picking { origin: event.pos };
+                ctx.submit_command(consts::cmd::SHOW_GLYPH_PICKER);
+                return;
+            }
+            State::Picking { .. } => return,
+            State::Placed { id } | State::Dragging { id, .. } => id,
+        };
+
+        if let Some(handle) = self.handle_for_point(session, id, event.pos) {
+            let start_transform = session
+                .component_for_id(id)
+                .map(|c| c.transform)
+                .unwrap_or(Affine::IDENTITY);
+            let pivot = session.component_origin(id);
+            self.state = State::Dragging {
+                id,
+                handle,
+                start_transform,
+                start_mouse: event.pos,
+                pivot,
+            };
+            ctx.set_active(true);
+        }
+    }
+
+    fn mouse_moved(&mut self, event: &MouseEvent, ctx: &mut EventCtx, session: &mut EditSession, _env: &Env) {
+        let (id, handle, start_transform, start_mouse, pivot) = match self.state {
+            State::Dragging {
+                id,
+                handle,
+                start_transform,
+                start_mouse,
+                pivot,
+            } => (id, handle, start_transform, start_mouse, pivot),
+            _ => return,
+        };
+
+        let delta = event.pos - start_mouse;
+        let new_transform = match handle {
+            Handle::Move => Affine::translate(delta) * start_transform,
+            Handle::Scale => {
+                let base_len = start_mouse.distance(pivot);
+                let cur_len = event.pos.distance(pivot);
+                let scale = if base_len > 0.0 { cur_len / base_len } else { 1.0 };
+                pivot_transform(pivot, Affine::scale(scale)) * start_transform
+            }
+            Handle::Rotate => {
+                let start_angle = (start_mouse - pivot).atan2();
+                let cur_angle = (event.pos - pivot).atan2();
+                pivot_transform(pivot, Affine::rotate(cur_angle - start_angle)) * start_transform
+            }
+        };
+
+        session.update_component_transform(id, new_transform, EditType::Drag);
+        ctx.request_paint();
+    }
+
+    fn mouse_up(&mut self, _event: &MouseEvent, ctx: &mut EventCtx, _session: &mut EditSession, _env: &Env) {
+        if let State::Dragging { id, .. } = self.state {
+            self.state = State::Placed { id };
+            ctx.set_active(false);
+        }
+    }
+
+    fn command(&mut self, cmd: &Command, ctx: &mut EventCtx, session: &mut EditSession) -> bool {
+        if let State::Picking { origin } = self.state {
+            if let Some(base) = cmd.get(consts::cmd::COMPONENT_BASE_CHOSEN) {
+                self.place_component(session, base.clone(), origin);
+                ctx.request_paint();
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pivot_transform_rotates_around_pivot_not_origin() {
+        let pivot = Point::new(10.0, 0.0);
+        let quarter_turn = pivot_transform(pivot, Affine::rotate(std::f64::consts::FRAC_PI_2));
+
+        // a point sitting right next to the pivot should swing around it,
+        // landing well away from where a rotation about the global origin
+        // would send it.
+        let moved = quarter_turn * Point::new(11.0, 0.0);
+        assert!((moved.x - 10.0).abs() < 1e-9);
+        assert!((moved.y - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pivot_transform_scales_around_pivot() {
+        let pivot = Point::new(10.0, 10.0);
+        let double = pivot_transform(pivot, Affine::scale(2.0));
+
+        // the pivot itself is fixed...
+        assert_eq!(double * pivot, pivot);
+        // ...while a point 2 units away ends up 4 units away.
+        let moved = double * Point::new(12.0, 10.0);
+        assert_eq!(moved, Point::new(14.0, 10.0));
+    }
+
+    #[test]
+    fn handle_at_bbox_hits_rotate_corner_and_move() {
+        let bbox = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let corner = Point::new(100.0, 100.0);
+        let rotate_pt = corner + Vec2::new(ROTATE_HANDLE_OFFSET, -ROTATE_HANDLE_OFFSET);
+
+        assert_eq!(handle_at_bbox(bbox, rotate_pt), Some(Handle::Rotate));
+        assert_eq!(handle_at_bbox(bbox, corner), Some(Handle::Scale));
+        assert_eq!(handle_at_bbox(bbox, Point::new(50.0, 50.0)), Some(Handle::Move));
+    }
+
+    #[test]
+    fn handle_at_bbox_misses_outside_bbox() {
+        let bbox = Rect::new(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(handle_at_bbox(bbox, Point::new(200.0, 200.0)), None);
+    }
+}
@@ -3,7 +3,7 @@
 use druid::kurbo::{Affine, BezPath, Circle, Line, Shape, Vec2};
 use druid::widget::prelude::*;
 use druid::widget::{Painter, WidgetExt};
-use druid::{Color, Data, HotKey, KeyEvent, Rect, SysMods, WidgetPod};
+use druid::{Color, Data, FontFamily, HotKey, KeyEvent, Point, Rect, SysMods, TextLayout, WidgetPod};
 
 use crate::consts;
 use crate::tools::ToolId;
@@ -13,30 +13,100 @@ const TOOLBAR_ITEM_PADDING: f64 = 2.0;
 const TOOLBAR_ICON_PADDING: f64 = 5.0;
 const TOOLBAR_BORDER_STROKE_WIDTH: f64 = 2.0;
 const TOOLBAR_ITEM_STROKE_WIDTH: f64 = 1.5;
+const TOOLBAR_TOOLTIP_OFFSET: f64 = 6.0;
+const TOOLBAR_TOOLTIP_PADDING: f64 = 4.0;
+/// Conservative upper bound on how tall a one-line tooltip can get, used to
+/// size the paint insets below the toolbar's own layout rect.
+const TOOLBAR_TOOLTIP_MAX_HEIGHT: f64 = 40.0;
+/// Conservative upper bound on how far a tooltip can overhang the right
+/// edge of the last hovered item.
+const TOOLBAR_TOOLTIP_MAX_OVERHANG: f64 = 140.0;
 // TODO: move these to theme
 const TOOLBAR_BG_DEFAULT: Color = Color::grey8(0xDD);
 const TOOLBAR_BG_SELECTED: Color = Color::grey8(0xAD);
+const TOOLBAR_BG_HOVER: Color = Color::rgba8(0xFF, 0xFF, 0xFF, 0x50);
+const TOOLBAR_TOOLTIP_BG: Color = Color::grey8(0x33);
+const TOOLBAR_TOOLTIP_TEXT: Color = Color::WHITE;
+const TOOLBAR_SEPARATOR_WIDTH: f64 = 8.0;
+const TOOLBAR_OVERFLOW_WIDTH: f64 = 16.0;
 
 struct ToolbarItem {
     icon: BezPath,
     name: ToolId,
     hotkey: HotKey,
+    /// A short human-readable label for `hotkey`, e.g. `"Shift+U"`, shown
+    /// in the hover tooltip alongside `name`.
+    hotkey_label: &'static str,
+    /// Whether this item starts a new group: it is set off from the item
+    /// before it by an extra `TOOLBAR_SEPARATOR_WIDTH` gap, and no hairline
+    /// rule is drawn before it.
+    starts_group: bool,
 }
 
-/// The floating toolbar.
+/// Registers tools to build a [`Toolbar`] at runtime.
 ///
-/// This is a very hacky implementation to get us rolling; it is not very
-/// reusable, but can be refactored at a future date.
+/// Tools are appended in the order they should appear, left to right;
+/// [`ToolbarBuilder::add_separator`] marks a group boundary so related
+/// tools (the shape tools, say) can be visually clustered without the
+/// widget needing to know about specific tools ahead of time.
+#[derive(Default)]
+pub struct ToolbarBuilder {
+    items: Vec<ToolbarItem>,
+    next_starts_group: bool,
+}
+
+impl ToolbarBuilder {
+    pub fn new() -> Self {
+        ToolbarBuilder::default()
+    }
+
+    /// Register a tool with its icon and hotkey.
+    pub fn add_tool(
+        mut self,
+        name: ToolId,
+        icon: BezPath,
+        hotkey: HotKey,
+        hotkey_label: &'static str,
+    ) -> Self {
+        self.items.push(ToolbarItem {
+            name,
+            icon: constrain_path(icon),
+            hotkey,
+            hotkey_label,
+            starts_group: self.next_starts_group,
+        });
+        self.next_starts_group = false;
+        self
+    }
+
+    /// Mark a group boundary before the next registered tool.
+    pub fn add_separator(mut self) -> Self {
+        self.next_starts_group = true;
+        self
+    }
+
+    pub fn build(self) -> Toolbar {
+        Toolbar::new(self.items)
+    }
+}
+
+/// The floating toolbar.
 pub struct Toolbar {
     items: Vec<ToolbarItem>,
     selected: usize,
     widgets: Vec<WidgetPod<bool, Box<dyn Widget<bool>>>>,
-}
-
-/// A wrapper around control UI elements, drawing a drop shadow & rounded rect
-pub struct FloatingPanel<W> {
-    hide_panel: bool,
-    inner: W,
+    /// Each item's layout rect from the most recent `layout` pass, recorded
+    /// as a hitbox so `paint` can test it against the *current* pointer
+    /// position rather than a hover index computed from a stale frame.
+    hitboxes: Vec<Rect>,
+    /// The pointer position last reported by a `MouseMove` event, in the
+    /// toolbar's local coordinate space.
+    pointer_pos: Option<Point>,
+    tooltip_layout: TextLayout<String>,
+    /// Set during `layout` when there isn't room for every registered tool;
+    /// `paint` clips to the available width and draws an overflow marker
+    /// instead of spilling tools past the toolbar's bounds.
+    overflow: bool,
 }
 
 impl Toolbar {
@@ -66,6 +136,10 @@ impl Toolbar {
             items,
             widgets,
             selected: 0,
+            hitboxes: Vec::new(),
+            pointer_pos: None,
+            tooltip_layout: TextLayout::from_text(String::new()),
+            overflow: false,
         }
     }
 
@@ -75,6 +149,13 @@ impl Toolbar {
             .find(|tool| tool.hotkey.matches(key))
             .map(|tool| tool.name)
     }
+
+    /// The index of the item under the pointer, tested against this
+    /// frame's `hitboxes` rather than a value cached from the previous one.
+    fn hovered_index(&self) -> Option<usize> {
+        let pos = self.pointer_pos?;
+        self.hitboxes.iter().position(|rect| rect.contains(pos))
+    }
 }
 
 impl<T: Data> Widget<T> for Toolbar {
@@ -101,6 +182,18 @@ impl<T: Data> Widget<T> for Toolbar {
         if matches!(event, Event::MouseDown(_) | Event::MouseUp(_)) {
             ctx.set_handled();
         }
+
+        match event {
+            Event::MouseMove(mouse) => {
+                self.pointer_pos = Some(mouse.pos);
+                ctx.request_paint();
+            }
+            Event::MouseLeave(_) => {
+                self.pointer_pos = None;
+                ctx.request_paint();
+            }
+            _ => (),
+        }
     }
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &T, env: &Env) {
@@ -117,141 +210,281 @@ impl<T: Data> Widget<T> for Toolbar {
     fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &T, env: &Env) -> Size {
         let constraints = BoxConstraints::tight(TOOLBAR_ITEM_SIZE);
         let mut x_pos = 0.0;
+        self.hitboxes.clear();
 
-        for child in self.widgets.iter_mut() {
+        for (item, child) in self.items.iter().zip(self.widgets.iter_mut()) {
+            if item.starts_group {
+                x_pos += TOOLBAR_SEPARATOR_WIDTH;
+            }
             // data doesn't matter here
             let size = child.layout(ctx, &constraints, &false, env);
-            child.set_layout_rect(ctx, &false, env, Rect::from_origin_size((x_pos, 0.0), size));
+            let rect = Rect::from_origin_size((x_pos, 0.0), size);
+            child.set_layout_rect(ctx, &false, env, rect);
+            self.hitboxes.push(rect);
             x_pos += TOOLBAR_ITEM_SIZE.width + TOOLBAR_ITEM_PADDING;
         }
 
         // Size doesn't account for stroke etc
-        bc.constrain(Size::new(
-            x_pos - TOOLBAR_ITEM_PADDING,
-            TOOLBAR_ITEM_SIZE.height,
-        ))
+        let content_width = x_pos - TOOLBAR_ITEM_PADDING;
+        self.overflow = content_width > bc.max().width;
+        let width = if self.overflow {
+            bc.max().width
+        } else {
+            content_width
+        };
+
+        // The hover tooltip paints below and to the right of the toolbar's
+        // own rect; declare that overflow so a container like `Board` knows
+        // not to clip it away.
+        ctx.set_paint_insets((
+            0.0,
+            0.0,
+            TOOLBAR_TOOLTIP_MAX_OVERHANG,
+            TOOLBAR_TOOLTIP_OFFSET + TOOLBAR_TOOLTIP_MAX_HEIGHT,
+        ));
+
+        bc.constrain(Size::new(width, TOOLBAR_ITEM_SIZE.height))
     }
 
     fn paint(&mut self, ctx: &mut PaintCtx, _data: &T, env: &Env) {
-        for (i, child) in self.widgets.iter_mut().enumerate() {
-            let is_selected = i == self.selected;
-            child.paint(ctx, &is_selected, env);
+        let frame = ctx.size().to_rect();
+        ctx.with_save(|ctx| {
+            if self.overflow {
+                ctx.clip(frame);
+            }
+            for (i, child) in self.widgets.iter_mut().enumerate() {
+                let is_selected = i == self.selected;
+                child.paint(ctx, &is_selected, env);
+            }
+        });
+
+        // Determined against this frame's hitboxes and the last reported
+        // pointer position, so a toolbar that just re-laid-out still
+        // highlights the correct item instead of one frame behind.
+        let hovered = self.hovered_index().filter(|&i| i != self.selected);
+        if let Some(i) = hovered {
+            let rect = self.hitboxes[i];
+            ctx.fill(rect, &TOOLBAR_BG_HOVER);
+
+            let item = &self.items[i];
+            let text = format!("{} ({})", item.name, item.hotkey_label);
+            self.tooltip_layout.set_text(text);
+            self.tooltip_layout.set_text_color(TOOLBAR_TOOLTIP_TEXT);
+            self.tooltip_layout.set_font(FontFamily::SYSTEM_UI);
+            self.tooltip_layout.rebuild_if_needed(ctx.text(), env);
+
+            let text_size = self.tooltip_layout.size();
+            let tooltip_size = text_size
+                + Vec2::new(TOOLBAR_TOOLTIP_PADDING * 2.0, TOOLBAR_TOOLTIP_PADDING * 2.0);
+            let origin = Point::new(
+                rect.min_x(),
+                rect.max_y() + TOOLBAR_TOOLTIP_OFFSET,
+            );
+            let tooltip_rect = Rect::from_origin_size(origin, tooltip_size).to_rounded_rect(3.0);
+            ctx.fill(tooltip_rect, &TOOLBAR_TOOLTIP_BG);
+            self.tooltip_layout.draw(
+                ctx,
+                origin + Vec2::new(TOOLBAR_TOOLTIP_PADDING, TOOLBAR_TOOLTIP_PADDING),
+            );
         }
 
         let stroke_inset = TOOLBAR_BORDER_STROKE_WIDTH / 2.0;
-        for child in self.widgets.iter().skip(1) {
+        for (item, child) in self.items.iter().zip(self.widgets.iter()).skip(1) {
+            if item.starts_group {
+                continue;
+            }
             let child_frame = child.layout_rect();
+            // items past the available width are clipped away; so are their rules
+            if child_frame.min_x() > frame.max_x() {
+                break;
+            }
             let line = Line::new(
                 (child_frame.min_x() - stroke_inset, child_frame.min_y()),
                 (child_frame.min_x() - stroke_inset, child_frame.max_y()),
             );
             ctx.stroke(line, &Color::BLACK, TOOLBAR_BORDER_STROKE_WIDTH);
         }
+
+        if self.overflow {
+            let strip = Rect::from_origin_size(
+                (frame.max_x() - TOOLBAR_OVERFLOW_WIDTH, frame.min_y()),
+                Size::new(TOOLBAR_OVERFLOW_WIDTH, frame.height()),
+            );
+            ctx.fill(strip, &TOOLBAR_TOOLTIP_BG.with_alpha(0.6));
+            let dot_x = strip.center().x;
+            for dy in [-6.0, 0.0, 6.0] {
+                let dot = Circle::new((dot_x, strip.center().y + dy), 1.5);
+                ctx.fill(dot, &Color::WHITE);
+            }
+        }
+    }
+}
+
+/// A single overlay placed at a fixed position within a [`Board`].
+struct BoardChild<T> {
+    widget: WidgetPod<T, Box<dyn Widget<T>>>,
+    origin: Point,
+    size: Size,
+    /// Independent of `Board::hide_in_preview`; lets a panel (e.g. a
+    /// minimap the user has dismissed) stay hidden regardless of preview
+    /// state.
+    visible: bool,
+}
+
+impl<T: Data> BoardChild<T> {
+    fn is_shown(&self, hide_in_preview: bool) -> bool {
+        self.visible && !hide_in_preview
     }
 }
 
-impl<W> FloatingPanel<W> {
-    pub fn new(inner: W) -> Self {
-        FloatingPanel {
-            hide_panel: false,
-            inner,
+/// An absolute-positioning overlay container.
+///
+/// Where [`Toolbar`] lays out its items in a row, `Board` places each child
+/// at an explicit canvas position and size, so the editor can stack several
+/// floating overlays -- the toolbar, a coordinate readout, a glyph minimap
+/// -- independently of one another. Each child gets the same drop-shadow +
+/// rounded-rect treatment the old single-child `FloatingPanel` drew, and
+/// all children honor `TOGGLE_PREVIEW_TOOL` together, though any child can
+/// also be hidden on its own via `set_child_visible`.
+pub struct Board<T> {
+    children: Vec<BoardChild<T>>,
+    hide_in_preview: bool,
+}
+
+impl<T: Data> Board<T> {
+    pub fn new() -> Self {
+        Board {
+            children: Vec::new(),
+            hide_in_preview: false,
+        }
+    }
+
+    /// Add a panel at a fixed `origin` and `size` within the board.
+    pub fn add_child(mut self, widget: impl Widget<T> + 'static, origin: Point, size: Size) -> Self {
+        self.children.push(BoardChild {
+            widget: WidgetPod::new(widget.boxed()),
+            origin,
+            size,
+            visible: true,
+        });
+        self
+    }
+
+    /// Show or hide the child at `index`, independent of preview mode.
+    pub fn set_child_visible(&mut self, ctx: &mut EventCtx, index: usize, visible: bool) {
+        if let Some(child) = self.children.get_mut(index) {
+            child.visible = visible;
+            ctx.request_paint();
         }
     }
+}
 
-    /// return a reference to the inner widget.
-    pub fn inner(&self) -> &W {
-        &self.inner
+impl<T: Data> Default for Board<T> {
+    fn default() -> Self {
+        Board::new()
     }
 }
 
-impl<T: Data, W: Widget<T>> Widget<T> for FloatingPanel<W> {
+impl<T: Data> Widget<T> for Board<T> {
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
-        self.inner.event(ctx, event, data, env);
         if let Event::Command(cmd) = event {
             if let Some(in_temporary_preview) = cmd.get(consts::cmd::TOGGLE_PREVIEW_TOOL) {
-                self.hide_panel = *in_temporary_preview;
+                self.hide_in_preview = *in_temporary_preview;
                 ctx.request_paint();
             }
         }
+
+        // Forward events to every child, even a hidden one, so its WidgetPod
+        // sees the MouseUp/HotChanged transitions it needs to clear hot and
+        // active state; only `paint` skips hidden children.
+        for child in self.children.iter_mut() {
+            child.widget.event(ctx, event, data, env);
+        }
     }
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
-        self.inner.lifecycle(ctx, event, data, env);
+        for child in self.children.iter_mut() {
+            child.widget.lifecycle(ctx, event, data, env);
+        }
     }
 
-    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
-        self.inner.update(ctx, old_data, data, env);
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        for child in self.children.iter_mut() {
+            child.widget.update(ctx, data, env);
+        }
     }
 
     fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
-        let size = self.inner.layout(ctx, bc, data, env);
-        ctx.set_paint_insets((0., 6.0, 6.0, 0.));
-        size
+        let mut content_size = Size::ZERO;
+        for child in self.children.iter_mut() {
+            let constraints = BoxConstraints::tight(child.size);
+            child.widget.layout(ctx, &constraints, data, env);
+            let rect = Rect::from_origin_size(child.origin, child.size);
+            child.widget.set_layout_rect(ctx, data, env, rect);
+            // the drop shadow extends past each panel's own rect
+            ctx.set_paint_insets((0., 6.0, 6.0, 0.));
+            content_size = Size::new(
+                content_size.width.max(rect.max_x()),
+                content_size.height.max(rect.max_y()),
+            );
+        }
+        // A board only needs to be as big as the overlays placed within it;
+        // never report an unbounded size back up the tree even if given
+        // loose constraints, the same pitfall `Toolbar::layout` avoids.
+        bc.constrain(content_size)
     }
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
-        if self.hide_panel {
-            return;
+        let hide_in_preview = self.hide_in_preview;
+        for child in self.children.iter_mut() {
+            if !child.is_shown(hide_in_preview) {
+                continue;
+            }
+            let frame = Rect::from_origin_size(child.origin, child.size);
+            ctx.blurred_rect(frame + Vec2::new(2.0, 2.0), 4.0, &Color::grey(0.5));
+            let rounded = frame.to_rounded_rect(5.0);
+            ctx.fill(rounded, &TOOLBAR_BG_DEFAULT);
+            ctx.with_save(|ctx| {
+                // Clip to the panel's rect expanded by whatever paint insets
+                // the child declared during layout (e.g. Toolbar's hover
+                // tooltip), rather than the bare rect, so that overflow
+                // isn't silently cut off.
+                let insets = child.widget.paint_insets();
+                let clip = Rect::new(
+                    frame.x0 - insets.x0,
+                    frame.y0 - insets.y0,
+                    frame.x1 + insets.x1,
+                    frame.y1 + insets.y1,
+                );
+                ctx.clip(clip);
+                child.widget.paint(ctx, data, env);
+            });
+            ctx.stroke(rounded, &Color::BLACK, TOOLBAR_BORDER_STROKE_WIDTH);
         }
-        let frame = ctx.size().to_rect();
-        ctx.blurred_rect(frame + Vec2::new(2.0, 2.0), 4.0, &Color::grey(0.5));
-        let rounded = frame.to_rounded_rect(5.0);
-        ctx.fill(rounded, &TOOLBAR_BG_DEFAULT);
-        ctx.with_save(|ctx| {
-            ctx.clip(rounded);
-            self.inner.paint(ctx, data, env);
-        });
-        ctx.stroke(rounded, &Color::BLACK, TOOLBAR_BORDER_STROKE_WIDTH);
     }
 }
 
 impl Default for Toolbar {
     fn default() -> Self {
-        let select = ToolbarItem {
-            name: "Select",
-            icon: constrain_path(select_path()),
-            hotkey: HotKey::new(None, "v"),
-        };
-
-        let pen = ToolbarItem {
-            name: "Pen",
-            icon: constrain_path(pen_path()),
-            hotkey: HotKey::new(None, "p"),
-        };
-
-        let preview = ToolbarItem {
-            name: "Preview",
-            icon: constrain_path(preview_path()),
-            hotkey: HotKey::new(None, "h"),
-        };
-
-        let rectangle = ToolbarItem {
-            name: "Rectangle",
-            icon: constrain_path(rect_path()),
-            hotkey: HotKey::new(None, "u"),
-        };
-
-        let ellipse = ToolbarItem {
-            name: "Ellipse",
-            icon: constrain_path(ellipse_path()),
-            hotkey: HotKey::new(SysMods::Shift, "u"),
-        };
-
-        let knife = ToolbarItem {
-            name: "Knife",
-            icon: constrain_path(knife_path()),
-            hotkey: HotKey::new(None, "e"),
-        };
-
-        let measure = ToolbarItem {
-            name: "Measure",
-            icon: constrain_path(measure_path()),
-            hotkey: HotKey::new(None, "m"),
-        };
-
-        Toolbar::new(vec![
-            select, pen, knife, preview, measure, rectangle, ellipse,
-        ])
+        ToolbarBuilder::new()
+            .add_tool("Select", select_path(), HotKey::new(None, "v"), "V")
+            .add_separator()
+            .add_tool("Pen", pen_path(), HotKey::new(None, "p"), "P")
+            .add_tool("Knife", knife_path(), HotKey::new(None, "e"), "E")
+            .add_separator()
+            .add_tool("Rectangle", rect_path(), HotKey::new(None, "u"), "U")
+            .add_tool(
+                "Ellipse",
+                ellipse_path(),
+                HotKey::new(SysMods::Shift, "u"),
+                "Shift+U",
+            )
+            .add_separator()
+            .add_tool("Preview", preview_path(), HotKey::new(None, "h"), "H")
+            .add_separator()
+            .add_tool("Measure", measure_path(), HotKey::new(None, "m"), "M")
+            .add_tool("Component", component_path(), HotKey::new(None, "c"), "C")
+            .build()
     }
 }
 
@@ -401,14 +634,49 @@ fn ellipse_path() -> BezPath {
     bez
 }
 
+fn component_path() -> BezPath {
+    let mut bez = BezPath::new();
+
+    // Two overlapping squares, suggesting one glyph embedded inside another.
+    bez.move_to((0.0, 0.0));
+    bez.line_to((280.0, 0.0));
+    bez.line_to((280.0, 280.0));
+    bez.line_to((0.0, 280.0));
+    bez.close_path();
+
+    bez.move_to((140.0, 140.0));
+    bez.line_to((420.0, 140.0));
+    bez.line_to((420.0, 420.0));
+    bez.line_to((140.0, 420.0));
+    bez.close_path();
+    bez
+}
+
 fn measure_path() -> BezPath {
     let mut bez = BezPath::new();
 
-    // TODO: design icon
+    // A ruler: a long body with alternating long/short tick marks.
     bez.move_to((0.0, 0.0));
-    bez.line_to((200.0, 0.0));
-    bez.line_to((200.0, 20.0));
-    bez.line_to((0.0, 20.0));
+    bez.line_to((600.0, 0.0));
+    bez.line_to((600.0, 100.0));
+    bez.line_to((0.0, 100.0));
     bez.close_path();
+
+    let ticks: &[(f64, f64)] = &[
+        (60.0, 60.0),
+        (120.0, 40.0),
+        (180.0, 60.0),
+        (240.0, 40.0),
+        (300.0, 60.0),
+        (360.0, 40.0),
+        (420.0, 60.0),
+        (480.0, 40.0),
+        (540.0, 60.0),
+    ];
+    for &(x, tick_len) in ticks {
+        bez.move_to((x, 0.0));
+        bez.line_to((x, tick_len));
+    }
+
     bez
 }